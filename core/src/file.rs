@@ -1,12 +1,128 @@
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression as GzLevel;
+use bzip2::read::BzDecoder;
+use bzip2::write::BzEncoder;
+use bzip2::Compression as BzLevel;
+use tokio::sync::mpsc;
+
 use crate::frame::Frame;
 
+/// Magic trailer marking a valid frame-offset footer.
+const FOOTER_MAGIC: &[u8; 8] = b"RIMEIDX\0";
+
+/// Compression codec for a [`File`](struct.File.html)'s frame stream.
+///
+/// Only affects how frames are written; on read the codec is
+/// auto-detected from the file's leading magic bytes.
+///
+/// # Example
+///
+/// Writing with [`Compression::Gzip`] produces a gzip file that still
+/// round-trips transparently, since the codec is auto-detected again on
+/// read regardless of what's passed to `File::new`:
+///
+/// ```
+/// use core::{Frame, File, FileMode, Compression};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("compressed");
+///
+/// let mut frame = Frame::new();
+/// frame.set("foo", 123u8);
+/// {
+///   let mut file = File::new(path.to_str().unwrap(), FileMode::Write, Compression::Gzip);
+///   file.write_frame(&frame).unwrap();
+/// }
+///
+/// // gzip's magic bytes are right at the start of the file
+/// let bytes = std::fs::read(&path).unwrap();
+/// assert_eq!(&bytes[..2], &[0x1f, 0x8b]);
+///
+/// let mut file = File::new(path.to_str().unwrap(), FileMode::Read, Compression::None);
+/// let frame2 = file.read_frame().unwrap().unwrap();
+/// let val: &u8 = frame2.get("foo").unwrap();
+/// assert_eq!(*val, 123u8);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Store frames as raw bincode, with no compression.
+    None,
+    /// Compress the frame stream with gzip (magic bytes `0x1f 0x8b`).
+    Gzip,
+    /// Compress the frame stream with bzip2 (magic bytes `"BZh"`).
+    Bzip2,
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const BZIP2_MAGIC: [u8; 3] = *b"BZh";
+
+/// Sniff the compression codec of a reader from its leading magic bytes,
+/// without consuming any bytes.
+fn detect_compression<R: BufRead>(reader: &mut R) -> std::io::Result<Compression> {
+    let buf = reader.fill_buf()?;
+    if buf.len() >= GZIP_MAGIC.len() && buf[..GZIP_MAGIC.len()] == GZIP_MAGIC {
+        Ok(Compression::Gzip)
+    } else if buf.len() >= BZIP2_MAGIC.len() && buf[..BZIP2_MAGIC.len()] == BZIP2_MAGIC {
+        Ok(Compression::Bzip2)
+    } else {
+        Ok(Compression::None)
+    }
+}
+
+/// Peek at the number of frames recorded in a file's footer, if it has a
+/// valid one, without disturbing the file's read position.
+///
+/// The footer is always appended as plain bytes after any codec has been
+/// finalized (see [`File::flush`](struct.File.html#method.flush)), so this
+/// works regardless of the frame stream's [`Compression`](enum.Compression.html).
+fn read_footer_count(f: &mut std::fs::File) -> std::io::Result<Option<u64>> {
+    let file_len = f.metadata()?.len();
+    if file_len < 16 {
+        f.seek(SeekFrom::Start(0))?;
+        return Ok(None);
+    }
+    f.seek(SeekFrom::End(-8))?;
+    let mut magic = [0u8; 8];
+    f.read_exact(&mut magic)?;
+    if &magic != FOOTER_MAGIC {
+        f.seek(SeekFrom::Start(0))?;
+        return Ok(None);
+    }
+    f.seek(SeekFrom::End(-16))?;
+    let mut count_buf = [0u8; 8];
+    f.read_exact(&mut count_buf)?;
+    let count = u64::from_le_bytes(count_buf);
+    f.seek(SeekFrom::Start(0))?;
+    Ok(Some(count))
+}
+
 /// A convenience for reading and writing files of
 /// [`Frames`](struct.Frame.html).
 ///
+/// Every frame is length-prefixed on disk, and a footer of frame offsets
+/// is appended (as plain, uncompressed bytes, after any codec has been
+/// finalized) when the file is flushed or dropped, so for an uncompressed
+/// file [`read_frame_at`] can jump directly to frame `n` instead of
+/// scanning sequentially. Uncompressed files written without a valid
+/// footer (e.g. a crash mid-write) still work: the index is built lazily
+/// by scanning the length prefixes once. Compressed files support only
+/// sequential [`read_frame`] calls; `read_frame_at` returns an
+/// `Unsupported` error for them.
+///
+/// The footer is also used to make sequential [`read_frame`] calls aware
+/// of where the real frames end, so reading past the last frame returns
+/// a clean `Ok(None)` instead of misreading the footer as a bogus frame.
+///
+/// [`read_frame_at`]: #method.read_frame_at
+/// [`read_frame`]: #method.read_frame
+///
 /// # Example
 ///
 /// ```
-/// use core::{Frame, File, FileMode};
+/// use core::{Frame, File, FileMode, Compression};
 ///
 /// // set up an Frame
 /// let mut frame = Frame::new();
@@ -17,13 +133,13 @@ use crate::frame::Frame;
 /// let path = dir.path().join("bar");
 /// {
 ///   // open an File and write the frame
-///   let mut file = File::new(path.to_str().unwrap(), FileMode::Write);
+///   let mut file = File::new(path.to_str().unwrap(), FileMode::Write, Compression::None);
 ///   file.write_frame(&frame);
 /// }
 /// assert_eq!(path.is_file(), true);
 /// {
 ///   // open the file and read the frame
-///   let mut file = File::new(path.to_str().unwrap(), FileMode::Read);
+///   let mut file = File::new(path.to_str().unwrap(), FileMode::Read, Compression::None);
 ///   match file.read_frame().unwrap() {
 ///     Some(frame2) => {
 ///       // compare to original frame
@@ -34,15 +150,62 @@ use crate::frame::Frame;
 ///   };
 /// }
 /// ```
+///
+/// Reading a file to completion, the idiomatic way, after writing several
+/// frames to it:
+///
+/// ```
+/// use core::{Frame, File, FileMode, Compression};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("many");
+/// {
+///   let mut file = File::new(path.to_str().unwrap(), FileMode::Write, Compression::None);
+///   for i in 0..3u8 {
+///     let mut frame = Frame::new();
+///     frame.set("i", i);
+///     file.write_frame(&frame).unwrap();
+///   }
+/// }
+///
+/// let mut file = File::new(path.to_str().unwrap(), FileMode::Read, Compression::None);
+/// let mut seen = Vec::new();
+/// while let Some(frame) = file.read_frame().unwrap() {
+///   seen.push(*frame.get::<_, u8>("i").unwrap());
+/// }
+/// assert_eq!(seen, vec![0, 1, 2]);
+/// ```
 pub struct File {
-    reader: Option<std::io::BufReader<std::fs::File>>,
-    writer: Option<std::io::BufWriter<std::fs::File>>,
+    reader: Option<BufReader<Box<dyn Read + Send>>>,
+    writer: Option<BufWriter<Box<dyn Write + Send>>>,
+    path: String,
+    compression: Compression,
+    offsets: Vec<u64>,
+    next_offset: u64,
+    footer_written: bool,
+    /// Number of real frames in the file, learned from its footer when
+    /// opened for reading. `None` if the file has no valid footer, in
+    /// which case [`read_frame`](#method.read_frame) relies on a clean
+    /// EOF (there being no footer bytes to misread) instead.
+    frame_total: Option<u64>,
+    /// Number of frames [`read_frame`](#method.read_frame) has returned
+    /// so far.
+    frames_read: u64,
 }
 
 /// Different ways to open an [`File`](struct.File.html).
 pub enum FileMode {
     Read,
     Write,
+    /// Not yet supported: see [`File::new`](#method.new).
+    ///
+    /// Before the frame-offset footer was introduced, this opened the
+    /// file in plain OS append mode and worked fine. `File::new` now
+    /// rejects it outright: this is a deliberate, scope-limited decision
+    /// (not an incidental side effect of the footer fix it shipped
+    /// alongside) to avoid silently corrupting the footer/offset table of
+    /// an existing file, made in lieu of the larger work of re-deriving
+    /// that table from the file's existing frames.
     Append,
 }
 
@@ -52,23 +215,75 @@ impl File {
     /// # Arguments
     /// * `filename` - name of file to open
     /// * `mode` - [`FileMode`](enum.FileMode.html) to open the file in.
+    /// * `compression` - [`Compression`](enum.Compression.html) codec to write with.
+    ///   Ignored when `mode` is [`FileMode::Read`](enum.FileMode.html), since the
+    ///   codec is instead auto-detected from the file's magic bytes.
     ///
     /// # Panics
     /// * if the file cannot be opened
-    pub fn new<S: AsRef<str>>(filename: S, mode: FileMode) -> File
+    /// * if `mode` is [`FileMode::Append`](enum.FileMode.html): appending to
+    ///   an indexed frame file would require re-deriving the offset table
+    ///   from the file's existing frames and stripping its old footer,
+    ///   which isn't implemented yet. Use [`FileMode::Write`](enum.FileMode.html)
+    ///   to start a fresh file instead.
+    pub fn new<S: AsRef<str>>(filename: S, mode: FileMode, compression: Compression) -> File
     where
         S: std::fmt::Display
     {
+        if let FileMode::Append = mode {
+            panic!(
+                "FileMode::Append is not yet supported for {}: it would silently corrupt the \
+                 existing frame-offset footer. Use FileMode::Write to start a fresh file instead.",
+                filename
+            );
+        }
         let fname = filename.as_ref();
         let file = match mode {
             FileMode::Read => std::fs::OpenOptions::new().read(true).open(fname),
             FileMode::Write => std::fs::OpenOptions::new().create(true).truncate(true).write(true).open(fname),
-            FileMode::Append => std::fs::OpenOptions::new().create(true).append(true).open(fname),
+            FileMode::Append => unreachable!(),
         };
         match file {
-            Ok(f) => match mode {
-                FileMode::Read => File{reader: Some(std::io::BufReader::new(f)), writer: None},
-                _ => File{reader: None, writer: Some(std::io::BufWriter::new(f))},
+            Ok(mut f) => match mode {
+                FileMode::Read => {
+                    let frame_total = read_footer_count(&mut f).unwrap_or(None);
+                    let mut raw = BufReader::new(f);
+                    let codec = detect_compression(&mut raw).unwrap_or(Compression::None);
+                    let reader: Box<dyn Read + Send> = match codec {
+                        Compression::Gzip => Box::new(GzDecoder::new(raw)),
+                        Compression::Bzip2 => Box::new(BzDecoder::new(raw)),
+                        Compression::None => Box::new(raw),
+                    };
+                    File{
+                        reader: Some(BufReader::new(reader)),
+                        writer: None,
+                        path: fname.to_string(),
+                        compression: codec,
+                        offsets: Vec::new(),
+                        next_offset: 0,
+                        footer_written: true,
+                        frame_total,
+                        frames_read: 0,
+                    }
+                },
+                _ => {
+                    let writer: Box<dyn Write + Send> = match compression {
+                        Compression::Gzip => Box::new(GzEncoder::new(f, GzLevel::default())),
+                        Compression::Bzip2 => Box::new(BzEncoder::new(f, BzLevel::default())),
+                        Compression::None => Box::new(f),
+                    };
+                    File{
+                        reader: None,
+                        writer: Some(BufWriter::new(writer)),
+                        path: fname.to_string(),
+                        compression,
+                        offsets: Vec::new(),
+                        next_offset: 0,
+                        footer_written: false,
+                        frame_total: None,
+                        frames_read: 0,
+                    }
+                },
             },
             Err(e) => panic!("cannot open file {}: {:?}", filename, e),
         }
@@ -80,6 +295,12 @@ impl File {
     /// which will return a `None` value. All other errors are propagated
     /// up.
     ///
+    /// If the file has a frame-offset footer (see
+    /// [`flush`](#method.flush)), this stops returning frames once it has
+    /// read that many, so the footer itself is never misread as a frame.
+    /// Files with no footer rely on a clean EOF right after the last
+    /// frame instead.
+    ///
     /// # Returns
     /// * Either an [`Frame`](struct.Frame.html) or `None`.
     ///
@@ -87,23 +308,186 @@ impl File {
     /// * Any io errors that occur.
     ///
     /// # Panics
-    /// * if we are trying to read a write-only file
+    /// * if we are trying to read to a write-only file
     pub fn read_frame(&mut self) -> std::io::Result<Option<Frame>> {
+        if let Some(total) = self.frame_total {
+            if self.frames_read >= total {
+                return Ok(None);
+            }
+        }
         match &mut self.reader {
             Some(r) => {
-                let mut frame = Frame::new();
-                match frame.read_from_stream(r) {
-                    Ok(_) => Ok(Some(frame)),
-                    Err(e) => match e.kind() {
-                        std::io::ErrorKind::UnexpectedEof => Ok(None),
-                        _ => Err(e),
-                    },
+                let mut len_buf = [0u8; 8];
+                match r.read_exact(&mut len_buf) {
+                    Ok(_) => {},
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(e) => return Err(e),
                 }
+                let len = u64::from_le_bytes(len_buf) as usize;
+                let mut buf = vec![0u8; len];
+                r.read_exact(&mut buf)?;
+                let mut frame = Frame::new();
+                frame.read_from_stream(&mut buf.as_slice())?;
+                self.frames_read += 1;
+                Ok(Some(frame))
             },
             None => panic!("trying to read to a write-only file"),
         }
     }
 
+    /// Read frame number `n` (zero-indexed) without reading the frames
+    /// before it.
+    ///
+    /// The first call loads the frame-offset index: from the footer if
+    /// the file has one, otherwise by scanning the file once to build it.
+    ///
+    /// # Arguments
+    /// * `n` - index of the frame to read
+    ///
+    /// # Returns
+    /// * `None` if `n` is beyond the last frame in the file.
+    ///
+    /// # Errors
+    /// * Any io errors that occur.
+    /// * An [`Unsupported`](std::io::ErrorKind::Unsupported) error if the
+    ///   file is compressed, since compressed streams cannot be seeked into.
+    ///
+    /// # Panics
+    /// * if we are trying to read to a write-only file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::{Frame, File, FileMode, Compression};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("indexed");
+    /// {
+    ///   let mut file = File::new(path.to_str().unwrap(), FileMode::Write, Compression::None);
+    ///   for i in 0..3u8 {
+    ///     let mut frame = Frame::new();
+    ///     frame.set("i", i);
+    ///     file.write_frame(&frame).unwrap();
+    ///   }
+    /// }
+    ///
+    /// let mut file = File::new(path.to_str().unwrap(), FileMode::Read, Compression::None);
+    /// assert_eq!(file.len().unwrap(), 3);
+    ///
+    /// // jump straight to frame 2 without reading frames 0 and 1 first
+    /// let frame = file.read_frame_at(2).unwrap().unwrap();
+    /// assert_eq!(*frame.get::<_, u8>("i").unwrap(), 2);
+    ///
+    /// assert!(file.read_frame_at(3).unwrap().is_none());
+    /// ```
+    pub fn read_frame_at(&mut self, n: usize) -> std::io::Result<Option<Frame>> {
+        if self.reader.is_none() {
+            panic!("trying to read to a write-only file");
+        }
+        if self.compression != Compression::None {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "random access requires an uncompressed file",
+            ));
+        }
+        self.load_index()?;
+        if n >= self.offsets.len() {
+            return Ok(None);
+        }
+        let mut raw = std::fs::File::open(&self.path)?;
+        raw.seek(SeekFrom::Start(self.offsets[n]))?;
+        let mut r = BufReader::new(raw);
+        let mut len_buf = [0u8; 8];
+        r.read_exact(&mut len_buf)?;
+        let len = u64::from_le_bytes(len_buf) as usize;
+        let mut buf = vec![0u8; len];
+        r.read_exact(&mut buf)?;
+        let mut frame = Frame::new();
+        frame.read_from_stream(&mut buf.as_slice())?;
+        Ok(Some(frame))
+    }
+
+    /// Number of frames in the file, loading the frame-offset index if
+    /// it hasn't been loaded yet.
+    ///
+    /// # Errors
+    /// * Any io errors that occur while loading the index.
+    /// * An [`Unsupported`](std::io::ErrorKind::Unsupported) error if the
+    ///   file is compressed and has no valid footer, since the fallback
+    ///   sequential scan reads raw (still-compressed) bytes and can't make
+    ///   sense of them without the footer telling it where frames start.
+    pub fn len(&mut self) -> std::io::Result<usize> {
+        self.load_index()?;
+        Ok(self.offsets.len())
+    }
+
+    /// Load `self.offsets` from the footer, or by a sequential scan if
+    /// there is no valid footer. A no-op if the index is already loaded.
+    ///
+    /// The footer is always plain, uncompressed bytes (see
+    /// [`flush`](#method.flush)), so it can be read regardless of
+    /// [`Compression`](enum.Compression.html). The sequential-scan
+    /// fallback, however, walks the raw bytes on disk directly, which
+    /// only makes sense for an uncompressed file; a compressed file with
+    /// no valid footer (e.g. a crash before the final flush) returns an
+    /// `Unsupported` error instead of scanning garbage.
+    fn load_index(&mut self) -> std::io::Result<()> {
+        if !self.offsets.is_empty() {
+            return Ok(());
+        }
+        let mut raw = std::fs::File::open(&self.path)?;
+        let file_len = raw.metadata()?.len();
+        if file_len >= 16 {
+            raw.seek(SeekFrom::End(-8))?;
+            let mut magic = [0u8; 8];
+            raw.read_exact(&mut magic)?;
+            if &magic == FOOTER_MAGIC {
+                raw.seek(SeekFrom::End(-16))?;
+                let mut count_buf = [0u8; 8];
+                raw.read_exact(&mut count_buf)?;
+                let count = u64::from_le_bytes(count_buf) as usize;
+                let offsets_len = count as u64 * 8;
+                if file_len >= 16 + offsets_len {
+                    raw.seek(SeekFrom::End(-16 - offsets_len as i64))?;
+                    let mut offsets = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        let mut b = [0u8; 8];
+                        raw.read_exact(&mut b)?;
+                        offsets.push(u64::from_le_bytes(b));
+                    }
+                    self.offsets = offsets;
+                    return Ok(());
+                }
+            }
+        }
+        if self.compression != Compression::None {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "indexing a compressed file with no valid footer requires scanning raw, \
+                 still-compressed bytes, which isn't supported",
+            ));
+        }
+        // no valid footer: fall back to a sequential scan to build the index lazily
+        raw.seek(SeekFrom::Start(0))?;
+        let mut r = BufReader::new(raw);
+        let mut offsets = Vec::new();
+        let mut pos: u64 = 0;
+        loop {
+            let mut len_buf = [0u8; 8];
+            match r.read_exact(&mut len_buf) {
+                Ok(_) => {},
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            offsets.push(pos);
+            let len = u64::from_le_bytes(len_buf);
+            r.seek_relative(len as i64)?;
+            pos += 8 + len;
+        }
+        self.offsets = offsets;
+        Ok(())
+    }
+
     /// Write a frame to the file.
     ///
     /// # Arguments
@@ -116,8 +500,168 @@ impl File {
     /// * if we are trying to write a read-only file
     pub fn write_frame(&mut self, frame: &Frame) -> std::io::Result<()> {
         match &mut self.writer {
-            Some(w) => frame.write_to_stream(w),
+            Some(w) => {
+                let mut buf = Vec::new();
+                frame.write_to_stream(&mut buf)?;
+                let len = buf.len() as u64;
+                w.write_all(&len.to_le_bytes())?;
+                w.write_all(&buf)?;
+                self.offsets.push(self.next_offset);
+                self.next_offset += 8 + len;
+                Ok(())
+            },
             None => panic!("trying to write to a read-only file"),
         }
     }
+
+    /// Flush pending writes and append the frame-offset footer, so
+    /// [`read_frame_at`](#method.read_frame_at) can index this file
+    /// without a sequential scan. A no-op if already flushed, or if
+    /// this file was opened for reading.
+    ///
+    /// This finalizes and closes the (possibly compressed) frame writer,
+    /// then appends the footer as plain, uncompressed bytes directly to
+    /// the underlying file, so it stays readable regardless of
+    /// [`Compression`](enum.Compression.html). Calling this is therefore
+    /// terminal: no more frames can be written afterwards.
+    ///
+    /// # Errors
+    /// * Any io errors that occur.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        if self.footer_written {
+            return Ok(());
+        }
+        if let Some(mut w) = self.writer.take() {
+            w.flush()?;
+            drop(w);
+            let mut raw = std::fs::OpenOptions::new().append(true).open(&self.path)?;
+            for off in &self.offsets {
+                raw.write_all(&off.to_le_bytes())?;
+            }
+            raw.write_all(&(self.offsets.len() as u64).to_le_bytes())?;
+            raw.write_all(FOOTER_MAGIC)?;
+            raw.flush()?;
+        }
+        self.footer_written = true;
+        Ok(())
+    }
+
+    /// Move this File onto a background `tokio` task, returning a
+    /// [`FrameSink`](struct.FrameSink.html) handle.
+    ///
+    /// This decouples module processing latency from storage latency:
+    /// [`FrameSink::send`](struct.FrameSink.html#method.send) only
+    /// enqueues the frame onto a channel and returns, while the spawned
+    /// task does the actual (possibly compressed) serialization. Only
+    /// the task touches the file after this call, so no locking is
+    /// needed between it and the returned handle.
+    ///
+    /// # Panics
+    /// * if this File was not opened for writing
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::{Frame, File, FileMode, Compression};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///   let dir = tempfile::tempdir().unwrap();
+    ///   let path = dir.path().join("sink");
+    ///
+    ///   let file = File::new(path.to_str().unwrap(), FileMode::Write, Compression::None);
+    ///   let sink = file.spawn_writer();
+    ///   for i in 0..3u8 {
+    ///     let mut frame = Frame::new();
+    ///     frame.set("i", i);
+    ///     if sink.send(frame).await.is_err() {
+    ///       panic!("background writer task is no longer running");
+    ///     }
+    ///   }
+    ///   sink.finish().await.unwrap();
+    ///
+    ///   let mut file = File::new(path.to_str().unwrap(), FileMode::Read, Compression::None);
+    ///   let mut seen = Vec::new();
+    ///   while let Some(frame) = file.read_frame().unwrap() {
+    ///     seen.push(*frame.get::<_, u8>("i").unwrap());
+    ///   }
+    ///   assert_eq!(seen, vec![0, 1, 2]);
+    /// }
+    /// ```
+    pub fn spawn_writer(self) -> FrameSink {
+        if self.writer.is_none() {
+            panic!("trying to spawn a writer for a read-only file");
+        }
+        let mut file = self;
+        let (tx, mut rx) = mpsc::channel::<Frame>(FRAME_SINK_CAPACITY);
+        let task = tokio::spawn(async move {
+            while let Some(frame) = rx.recv().await {
+                file.write_frame(&frame)?;
+            }
+            file.flush()
+        });
+        FrameSink{inner: FrameSinkInner::Active{tx, task}}
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Channel capacity for a [`FrameSink`](struct.FrameSink.html)'s backlog
+/// of not-yet-serialized frames.
+const FRAME_SINK_CAPACITY: usize = 64;
+
+enum FrameSinkInner {
+    Active{tx: mpsc::Sender<Frame>, task: tokio::task::JoinHandle<std::io::Result<()>>},
+    Noop,
+}
+
+/// A handle to a [`File`](struct.File.html) writer running on a background
+/// `tokio` task, returned by [`File::spawn_writer`](struct.File.html#method.spawn_writer).
+pub struct FrameSink {
+    inner: FrameSinkInner,
+}
+
+impl FrameSink {
+    /// A sink that discards every frame sent to it, without spawning a
+    /// background task.
+    ///
+    /// Useful when writing is conditionally disabled (e.g. a `--no-output`
+    /// flag), so callers don't need a separate code path: sends are
+    /// dropped cheaply instead of serializing to nowhere.
+    pub fn noop() -> FrameSink {
+        FrameSink{inner: FrameSinkInner::Noop}
+    }
+
+    /// Enqueue a frame to be written in the background.
+    ///
+    /// # Errors
+    /// * the frame, if the background task has already stopped (e.g.
+    ///   after hitting an io error)
+    pub async fn send(&self, frame: Frame) -> Result<(), Frame> {
+        match &self.inner {
+            FrameSinkInner::Active{tx, ..} => tx.send(frame).await.map_err(|e| e.0),
+            FrameSinkInner::Noop => Ok(()),
+        }
+    }
+
+    /// Flush and close the writer, joining the background task.
+    ///
+    /// # Errors
+    /// * Any io error the background task hit while writing.
+    pub async fn finish(self) -> std::io::Result<()> {
+        match self.inner {
+            FrameSinkInner::Active{tx, task} => {
+                drop(tx);
+                match task.await {
+                    Ok(result) => result,
+                    Err(e) => Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())),
+                }
+            },
+            FrameSinkInner::Noop => Ok(()),
+        }
+    }
 }
@@ -4,8 +4,13 @@ use std::collections::HashMap;
 use bincode::{serialize_into, deserialize_from};
 
 /// Base trait for any serializable object in an Frame.
+///
+/// `Send + Sync` is required so that a [`Frame`](struct.Frame.html) can be
+/// handed off between the `tokio` tasks that make up a pipelined
+/// [`Tray`](struct.Tray.html). `Debug` is required so that values can be
+/// pretty-printed generically, e.g. by the `Tray` REPL's `get` command.
 #[typetag::serde]
-pub trait Serializeable {
+pub trait Serializeable: Send + Sync + std::fmt::Debug {
     /// Convert to an `Any` reference.
     fn as_any(&self) -> &dyn Any;
     /// Convert to a mutable `Any` reference.
@@ -167,6 +172,75 @@ impl Frame {
         }
     }
 
+    /// Get a read-only `&dyn Serializeable` reference at a specific key,
+    /// without needing to know the concrete type ahead of time.
+    ///
+    /// This also checks the parent frame (recursively) for a match, same
+    /// as [`get`](#method.get). Useful for generic inspection, e.g. the
+    /// `Tray` REPL's `get` command.
+    ///
+    /// # Arguments
+    /// * `key` - the key to lookup
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::Frame;
+    ///
+    /// let mut x = Frame::new();
+    /// x.set("foo", String::from("Bar"));
+    ///
+    /// assert_eq!(format!("{:?}", x.get_any("foo").unwrap()), "\"Bar\"");
+    /// assert!(x.get_any("missing").is_none());
+    /// ```
+    pub fn get_any<S: AsRef<str>>(&self, key: S) -> Option<&dyn Serializeable> {
+        match self.data.get(key.as_ref()) {
+            Some(v) => Some(v.as_ref()),
+            None => match &self.parent {
+                Some(parent) => parent.get_any(key),
+                None => None,
+            },
+        }
+    }
+
+    /// List this frame's keys, including any inherited from parent frames
+    /// (walked recursively). A key already present in a more-derived frame
+    /// shadows the same key in an ancestor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::Frame;
+    /// use std::sync::Arc;
+    ///
+    /// let mut parent = Frame::new();
+    /// parent.set("foo", String::from("from parent"));
+    ///
+    /// let mut child = Frame::new_with_parent(Arc::new(parent));
+    /// child.set("bar", String::from("from child"));
+    ///
+    /// let mut keys: Vec<String> = child.keys().collect();
+    /// keys.sort();
+    /// assert_eq!(keys, vec!["bar".to_string(), "foo".to_string()]);
+    /// ```
+    pub fn keys(&self) -> impl Iterator<Item = String> {
+        let mut out = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        self.collect_keys(&mut out, &mut seen);
+        out.into_iter()
+    }
+
+    fn collect_keys(&self, out: &mut Vec<String>, seen: &mut std::collections::HashSet<String>) {
+        for k in self.data.keys() {
+            if seen.insert(k.clone()) {
+                out.push(k.clone());
+            }
+        }
+        if let Some(parent) = &self.parent {
+            parent.collect_keys(out, seen);
+        }
+    }
+
     /// Set a value at a specified key.
     ///
     /// The value is cloned into a `Box` in order to add it to the frame.
@@ -1,3 +1,6 @@
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::sync::Mutex;
+
 use crate::frame::Frame;
 
 /// Module function type.
@@ -10,7 +13,10 @@ pub type FunctionModule = fn(_: Frame) -> Frame;
 ///
 /// A trait with a `process` function that takes a Frame
 /// and returns a Frame.
-pub trait Module {
+///
+/// `Send + Sync` is required so a module can be shared (via `Arc`) across
+/// the `tokio` tasks that make up a [`Tray`](struct.Tray.html)'s pipeline.
+pub trait Module: Send + Sync {
     fn process(&self, _: Frame) -> Frame;
 }
 
@@ -45,7 +51,10 @@ impl From<FunctionModule> for SimpleModule
 ///
 /// A trait with a `start` function that takes nothing
 /// and returns a Frame or None.
-pub trait StartModule {
+///
+/// `Send + Sync` is required so the start module can run on its own
+/// `tokio` task as the head of a [`Tray`](struct.Tray.html)'s pipeline.
+pub trait StartModule: Send + Sync {
     fn start(&self) -> Option<Frame>;
 }
 
@@ -64,3 +73,144 @@ impl StartModule for InfiniteSource {
         Some(Frame::new())
     }
 }
+
+/// A `StartModule` that reads length-delimited frames from an arbitrary
+/// `Read`, yielding `None` at a clean end of stream.
+///
+/// Frames are framed the same way as [`File`](struct.File.html): an 8-byte
+/// little-endian length prefix followed by the bincode payload. This lets
+/// a `Tray` be composed as a Unix pipeline stage, reading frames that a
+/// previous stage wrote with [`StreamSink`](struct.StreamSink.html) (or a
+/// [`File`](struct.File.html)).
+///
+/// `start` takes `&self`, so the underlying reader is held behind a
+/// `Mutex` for interior mutability.
+pub struct StreamSource<R> {
+    reader: Mutex<BufReader<R>>,
+}
+
+impl<R: Read> StreamSource<R> {
+    /// Wrap an arbitrary `Read` as a `StreamSource`.
+    pub fn new(r: R) -> StreamSource<R> {
+        StreamSource{reader: Mutex::new(BufReader::new(r))}
+    }
+}
+
+impl StreamSource<std::io::Stdin> {
+    /// Read frames from stdin.
+    pub fn stdin() -> StreamSource<std::io::Stdin> {
+        StreamSource::new(std::io::stdin())
+    }
+}
+
+#[cfg(unix)]
+impl StreamSource<std::fs::File> {
+    /// Read frames from a raw file descriptor, e.g. one passed down by a
+    /// parent process for a pipeline stage that isn't stdin.
+    ///
+    /// # Safety
+    /// * `fd` must be a valid, open file descriptor that nothing else is
+    ///   using; ownership of it is transferred to the returned `StreamSource`.
+    pub unsafe fn from_raw_fd(fd: std::os::unix::io::RawFd) -> StreamSource<std::fs::File> {
+        use std::os::unix::io::FromRawFd;
+        StreamSource::new(std::fs::File::from_raw_fd(fd))
+    }
+}
+
+impl<R: Read + Send> StartModule for StreamSource<R> {
+    /// Read one length-delimited frame. An EOF exactly at a frame boundary
+    /// is treated as a clean end of stream (`None`), exactly as
+    /// [`File::read_frame`](struct.File.html#method.read_frame) does; any
+    /// other io or deserialization error is also treated as end of stream,
+    /// since `StartModule::start` has no way to report an error.
+    fn start(&self) -> Option<Frame> {
+        let mut r = self.reader.lock().unwrap();
+        let mut len_buf = [0u8; 8];
+        r.read_exact(&mut len_buf).ok()?;
+        let len = u64::from_le_bytes(len_buf) as usize;
+        let mut buf = vec![0u8; len];
+        r.read_exact(&mut buf).ok()?;
+        let mut frame = Frame::new();
+        frame.read_from_stream(&mut buf.as_slice()).ok()?;
+        Some(frame)
+    }
+}
+
+/// A terminal `Module` that writes processed frames to an arbitrary
+/// `Write`, then passes the frame through unchanged.
+///
+/// Frames are framed the same way as [`File`](struct.File.html): an 8-byte
+/// little-endian length prefix followed by the bincode payload, so a
+/// downstream `Tray` reading with [`StreamSource`](struct.StreamSource.html)
+/// (or a [`File`](struct.File.html)) sees the same stream.
+///
+/// `process` takes `&self`, so the underlying writer is held behind a
+/// `Mutex` for interior mutability.
+///
+/// # Example
+///
+/// Round-trip a frame through a `StreamSink`/`StreamSource` pair backed
+/// by an in-memory buffer, the same framing [`File`](struct.File.html) uses:
+///
+/// ```
+/// use core::{Frame, Module, StartModule, StreamSink, StreamSource};
+///
+/// let mut frame = Frame::new();
+/// frame.set("foo", 123u8);
+///
+/// let mut buf = Vec::new();
+/// {
+///   let sink = StreamSink::new(&mut buf);
+///   sink.process(frame);
+/// }
+///
+/// let source = StreamSource::new(buf.as_slice());
+/// let frame2 = source.start().unwrap();
+/// let val: &u8 = frame2.get("foo").unwrap();
+/// assert_eq!(*val, 123u8);
+/// ```
+pub struct StreamSink<W: Write> {
+    writer: Mutex<BufWriter<W>>,
+}
+
+impl<W: Write> StreamSink<W> {
+    /// Wrap an arbitrary `Write` as a `StreamSink`.
+    pub fn new(w: W) -> StreamSink<W> {
+        StreamSink{writer: Mutex::new(BufWriter::new(w))}
+    }
+}
+
+impl StreamSink<std::io::Stdout> {
+    /// Write frames to stdout.
+    pub fn stdout() -> StreamSink<std::io::Stdout> {
+        StreamSink::new(std::io::stdout())
+    }
+}
+
+#[cfg(unix)]
+impl StreamSink<std::fs::File> {
+    /// Write frames to a raw file descriptor, e.g. one passed down by a
+    /// parent process for a pipeline stage that isn't stdout.
+    ///
+    /// # Safety
+    /// * `fd` must be a valid, open file descriptor that nothing else is
+    ///   using; ownership of it is transferred to the returned `StreamSink`.
+    pub unsafe fn from_raw_fd(fd: std::os::unix::io::RawFd) -> StreamSink<std::fs::File> {
+        use std::os::unix::io::FromRawFd;
+        StreamSink::new(std::fs::File::from_raw_fd(fd))
+    }
+}
+
+impl<W: Write + Send> Module for StreamSink<W> {
+    fn process(&self, frame: Frame) -> Frame {
+        let mut buf = Vec::new();
+        if frame.write_to_stream(&mut buf).is_ok() {
+            let mut w = self.writer.lock().unwrap();
+            if w.write_all(&(buf.len() as u64).to_le_bytes()).is_ok() {
+                let _ = w.write_all(&buf);
+            }
+            let _ = w.flush();
+        }
+        frame
+    }
+}
@@ -1,3 +1,9 @@
+use std::io::{BufRead, Write as IoWrite};
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+
+use crate::frame::Frame;
 use crate::module::*;
 
 /// Tray of modules.
@@ -55,9 +61,29 @@ use crate::module::*;
 ///   tray.run_bounded(10).await;
 /// }
 /// ```
+/// Default channel capacity between pipeline stages; see
+/// [`set_max_in_flight`](#method.set_max_in_flight).
+const DEFAULT_MAX_IN_FLIGHT: usize = 8;
+
+/// Re-panic with a pipeline stage task's original payload, if it panicked.
+///
+/// `tokio::spawn`'d tasks turn a panic into an `Err(JoinError)` instead of
+/// unwinding the caller, so without this a panicking `Module` would be
+/// silently swallowed and `run_bounded` would return as if nothing went
+/// wrong. Propagating it keeps a module panic just as loud as it was
+/// when the pipeline ran as a plain synchronous loop.
+fn propagate_panic(res: Result<(), tokio::task::JoinError>) {
+    if let Err(e) = res {
+        if e.is_panic() {
+            std::panic::resume_unwind(e.into_panic());
+        }
+    }
+}
+
 pub struct Tray {
-    start_module: Box<dyn StartModule>,
-    modules: Vec<Box<dyn Module>>,
+    start_module: Arc<dyn StartModule>,
+    modules: Vec<Arc<dyn Module>>,
+    max_in_flight: usize,
 }
 
 impl Tray {
@@ -66,7 +92,7 @@ impl Tray {
     where
         S: StartModule,
     {
-        Tray{start_module: Box::new(s), modules: Vec::new()}
+        Tray{start_module: Arc::new(s), modules: Vec::new(), max_in_flight: DEFAULT_MAX_IN_FLIGHT}
     }
 
     /// Add a module to the Tray.
@@ -77,7 +103,7 @@ impl Tray {
     where
         M: Module,
     {
-        self.modules.push(Box::new(m));
+        self.modules.push(Arc::new(m));
     }
 
     /// Add a function module to the Tray.
@@ -89,6 +115,20 @@ impl Tray {
         self.add(SimpleModule::new(m))
     }
 
+    /// Set the channel capacity between pipeline stages.
+    ///
+    /// Each stage in the pipeline can be processing a different frame at
+    /// the same time; this bounds how many frames may be in flight (queued
+    /// between stages, including the head) at once, so a slow stage
+    /// applies backpressure instead of letting memory grow unbounded.
+    /// Defaults to 8.
+    ///
+    /// # Arguments
+    /// * `n` - maximum number of frames in flight per stage boundary
+    pub fn set_max_in_flight(&mut self, n: usize) -> () {
+        self.max_in_flight = n;
+    }
+
     /// Run the tray until it ends.
     pub async fn run(&self) -> () {
         self.run_bounded(std::u64::MAX).await;
@@ -96,20 +136,140 @@ impl Tray {
 
     /// Run the tray for `num` frames, or until it ends on its own.
     ///
+    /// Each module runs as its own `tokio` task, connected to its
+    /// neighbours by bounded `mpsc` channels, so module *i* can process
+    /// frame *k* while module *i+1* processes frame *k-1* instead of the
+    /// whole chain running strictly serially. `num` frames are injected
+    /// at the head of the pipeline, then the head is closed and the
+    /// pipeline drains to completion.
+    ///
     /// # Arguments
     /// * `num` - number of frames to execute
-    pub async fn run_bounded(&self, num:u64) -> () {
+    ///
+    /// # Panics
+    /// * if a `Module` panics while processing a frame, the panic is
+    ///   propagated out of this call (with its original payload), the
+    ///   same as it would be if the pipeline ran as a plain synchronous
+    ///   loop on the calling task.
+    pub async fn run_bounded(&self, num: u64) -> () {
+        let capacity = self.max_in_flight.max(1);
+
+        let start_module = self.start_module.clone();
+        let (head_tx, mut upstream) = mpsc::channel::<Frame>(capacity);
+        let producer = tokio::spawn(async move {
+            for _ in 0..num {
+                match start_module.start() {
+                    Some(fr) => {
+                        if head_tx.send(fr).await.is_err() {
+                            break;
+                        }
+                    },
+                    None => break,
+                }
+            }
+        });
+
+        let mut stages = Vec::with_capacity(self.modules.len());
+        for m in self.modules.iter().cloned() {
+            let (tx, rx) = mpsc::channel::<Frame>(capacity);
+            let mut up = upstream;
+            stages.push(tokio::spawn(async move {
+                while let Some(fr) = up.recv().await {
+                    if tx.send(m.process(fr)).await.is_err() {
+                        break;
+                    }
+                }
+            }));
+            upstream = rx;
+        }
+
+        // drain whatever the last stage forwards, to completion
+        let mut tail = upstream;
+        let drain = tokio::spawn(async move {
+            while tail.recv().await.is_some() {}
+        });
+
+        propagate_panic(producer.await);
+        for stage in stages {
+            propagate_panic(stage.await);
+        }
+        propagate_panic(drain.await);
+    }
+
+    /// Run the tray interactively: step through frames one at a time,
+    /// dropping into a line-based command loop between each.
+    ///
+    /// Unlike [`run_bounded`](#method.run_bounded), frames are run through
+    /// all modules sequentially on the calling task, so the REPL always has
+    /// a fully-processed "current" frame to inspect between steps.
+    ///
+    /// # Commands
+    /// * `next` / `n` - advance one frame through all modules
+    /// * `run N` - advance N frames
+    /// * `keys` - list the current frame's keys, including those inherited from parent frames
+    /// * `get <key>` - pretty-print the value at `<key>` via its `Debug` representation
+    /// * `quit` - exit the REPL
+    pub async fn run_repl(&self) -> () {
+        let stdin = std::io::stdin();
+        let mut current: Option<Frame> = None;
+        loop {
+            print!("> ");
+            let _ = std::io::stdout().flush();
+
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+                break;
+            }
+            let mut parts = line.trim().split_whitespace();
+            match parts.next() {
+                Some("next") | Some("n") => {
+                    if let Some(fr) = self.advance(1) {
+                        current = Some(fr);
+                    }
+                },
+                Some("run") => {
+                    let n: u64 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+                    if let Some(fr) = self.advance(n) {
+                        current = Some(fr);
+                    }
+                },
+                Some("keys") => match &current {
+                    Some(fr) => for k in fr.keys() {
+                        println!("{}", k);
+                    },
+                    None => println!("no current frame"),
+                },
+                Some("get") => match (parts.next(), &current) {
+                    (None, _) => println!("usage: get <key>"),
+                    (Some(_), None) => println!("no current frame"),
+                    (Some(key), Some(fr)) => match fr.get_any(key) {
+                        Some(v) => println!("{:?}", v),
+                        None => println!("no key \"{}\" in frame or parents", key),
+                    },
+                },
+                Some("quit") => break,
+                Some(other) => println!("unknown command: {}", other),
+                None => {},
+            }
+        }
+    }
+
+    /// Advance `num` frames through all modules sequentially, returning
+    /// the last frame produced, or `None` if the start module ended the
+    /// stream first.
+    fn advance(&self, num: u64) -> Option<Frame> {
+        let mut last = None;
         for _ in 0..num {
             match self.start_module.start() {
                 Some(mut fr) => {
                     for m in self.modules.iter() {
                         fr = m.process(fr);
                     }
+                    last = Some(fr);
                 },
-                None => {
-                    break;
-                }
+                None => break,
             }
         }
+        last
     }
 }